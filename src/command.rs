@@ -0,0 +1,144 @@
+//! Free-text command mode: turns imperative sentences like
+//! `add Sally to Engineering` or `list all` into a typed [`Command`] the
+//! main loop can dispatch to the corresponding [`Company`](crate::Company)
+//! methods, so the tool can be driven from a piped script instead of the
+//! interactive menus.
+
+/// Prepositions that separate the operands of a command (`add Sally to
+/// Engineering`, `move Amir from Sales`).
+const PREPOSITIONS: [&str; 3] = ["to", "from", "in"];
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Add { employee: String, department: String },
+    Transfer { employee: String, department: String },
+    List(ListTarget),
+    Remove { department: String },
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ListTarget {
+    All,
+    Department(String),
+}
+
+/// Parse a single line of input into a [`Command`], returning a helpful
+/// message instead of panicking on unknown verbs or malformed operands.
+pub fn parse(input: &str) -> Result<Command, String> {
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    let (verb, rest) = tokens
+        .split_first()
+        .ok_or_else(|| "Ingrese un comando".to_string())?;
+
+    match verb.to_lowercase().as_str() {
+        "add" | "hire" => {
+            let (employee, department) = split_operands(rest)
+                .ok_or_else(|| "Uso: add <empleado> to <departamento>".to_string())?;
+            Ok(Command::Add {
+                employee: normalize_name(&employee),
+                department: normalize_department(&department),
+            })
+        }
+        "move" | "transfer" => {
+            let (employee, department) = split_operands(rest)
+                .ok_or_else(|| "Uso: move <empleado> to <departamento>".to_string())?;
+            Ok(Command::Transfer {
+                employee: normalize_name(&employee),
+                department: normalize_department(&department),
+            })
+        }
+        "list" => {
+            let operand = strip_preposition(rest);
+            if operand.is_empty() || operand.to_lowercase() == "all" {
+                Ok(Command::List(ListTarget::All))
+            } else {
+                Ok(Command::List(ListTarget::Department(normalize_department(
+                    &operand,
+                ))))
+            }
+        }
+        "remove" => {
+            let department = strip_preposition(rest);
+            if department.is_empty() {
+                return Err("Uso: remove <departamento>".to_string());
+            }
+            Ok(Command::Remove {
+                department: normalize_department(&department),
+            })
+        }
+        "quit" => Ok(Command::Quit),
+        other => Err(format!("Comando desconocido: '{}'", other)),
+    }
+}
+
+/// Split the operands around the first preposition, returning the name on
+/// its left and the department on its right.
+fn split_operands(rest: &[&str]) -> Option<(String, String)> {
+    let position = rest
+        .iter()
+        .position(|token| PREPOSITIONS.contains(&token.to_lowercase().as_str()))?;
+
+    let left = rest[..position].join(" ");
+    let right = rest[position + 1..].join(" ");
+
+    if left.is_empty() || right.is_empty() {
+        None
+    } else {
+        Some((left, right))
+    }
+}
+
+/// Drop a leading preposition (`list in Engineering` -> `Engineering`) and
+/// join the remaining operand.
+fn strip_preposition(rest: &[&str]) -> String {
+    let start = match rest.first() {
+        Some(token) if PREPOSITIONS.contains(&token.to_lowercase().as_str()) => 1,
+        _ => 0,
+    };
+
+    rest[start..].join(" ")
+}
+
+fn normalize_department(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_supported_verbs() {
+        assert_eq!(
+            parse("add Sally to Engineering").unwrap(),
+            Command::Add {
+                employee: "Sally".to_string(),
+                department: "engineering".to_string(),
+            }
+        );
+        assert_eq!(
+            parse("move Amir to Sales").unwrap(),
+            Command::Transfer {
+                employee: "Amir".to_string(),
+                department: "sales".to_string(),
+            }
+        );
+        assert_eq!(parse("list all").unwrap(), Command::List(ListTarget::All));
+        assert_eq!(
+            parse("list Engineering").unwrap(),
+            Command::List(ListTarget::Department("engineering".to_string()))
+        );
+        assert_eq!(parse("quit").unwrap(), Command::Quit);
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_input() {
+        assert!(parse("dance Sally to Engineering").is_err());
+        assert!(parse("add Sally").is_err());
+    }
+}
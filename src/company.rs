@@ -5,26 +5,39 @@ use crate::{
     Database,
 };
 use cuid;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CompanyEvents {
-    DepartmentAdded(Rc<Department>),
-    EmployeeHired(Rc<Employee>),
+    DepartmentAdded(Department),
+    EmployeeHired(Employee),
+    EmployeeFired { id: String },
+    DepartmentRemoved { id: String },
+    EmployeeTransferred { id: String, new_department_id: String },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Department {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Employee {
     id: String,
     pub name: String,
     pub department_id: String,
 }
 
+/// Outcome of a fuzzy name lookup: an exact hit, the closest candidate
+/// within the edit-distance threshold, or nothing near enough to suggest.
+pub enum Match<'a> {
+    Exact(&'a Rc<Department>),
+    Suggestion(&'a Rc<Department>),
+    None,
+}
+
 #[derive(Default, Debug)]
 pub struct Company {
     pub departments: Vec<Rc<Department>>,
@@ -34,6 +47,7 @@ pub struct Company {
 
 impl AggregateRoot<CompanyEvents> for Company {
     fn apply(&mut self, event: CompanyEvents) -> () {
+        self.mutate(&event);
         self.events.push(DomainEvent { event })
     }
 
@@ -48,84 +62,155 @@ impl AggregateRoot<CompanyEvents> for Company {
 
 impl Repository<CompanyEvents, Company> for Company {
     fn get(db: &Database) -> Result<Self, Box<dyn Error>> {
-        let mut company = Company::default();
-        let mut departments_stmt = db.prepare("SELECT * FROM departments")?;
-        let mut employees_stmt = db.prepare("SELECT * FROM employees WHERE department_id = $1")?;
-
-        let departments = departments_stmt.query_map([], |row| {
-            Ok(Rc::new(Department {
-                id: row.get(0).unwrap(),
-                name: row.get(1).unwrap(),
-            }))
+        let mut events_stmt =
+            db.prepare("SELECT payload_json FROM events WHERE aggregate = ?1 ORDER BY sequence ASC")?;
+
+        let rows = events_stmt.query_map([Company::AGGREGATE], |row| {
+            let payload: String = row.get(0)?;
+            Ok(payload)
         })?;
 
-        for department in departments {
-            let dpt = department.unwrap();
+        let mut stream: Vec<CompanyEvents> = Vec::new();
+        for row in rows {
+            stream.push(serde_json::from_str(&row?)?);
+        }
+
+        Ok(Company::from_events(stream))
+    }
 
-            let employees = employees_stmt.query_map([&dpt.id], |row| {
-                Ok(Rc::new(Employee {
-                    id: row.get(0).unwrap(),
-                    name: row.get(1).unwrap(),
-                    department_id: row.get(2).unwrap(),
-                }))
-            })?;
+    fn save(&self, db: &Database) -> Result<usize, Box<dyn Error>> {
+        let events = self.get_uncommited_events();
 
-            for employee in employees {
-                company.employees.push(employee.unwrap().clone());
-            }
+        let tx = db.unchecked_transaction()?;
+
+        let mut sequence: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(sequence), 0) FROM events WHERE aggregate = ?1",
+            [Company::AGGREGATE],
+            |row| row.get(0),
+        )?;
 
-            company.departments.push(dpt.clone());
+        for domain_event in events.iter() {
+            sequence += 1;
+            let event_type = domain_event.event.event_type();
+            let payload = serde_json::to_string(&domain_event.event)?;
+
+            tx.execute(
+                "INSERT INTO events (id, aggregate, sequence, type, payload_json)
+                    values (?1, ?2, ?3, ?4, ?5)",
+                params![cuid::cuid2(), Company::AGGREGATE, sequence, event_type, payload],
+            )?;
+
+            self.project(&tx, &domain_event.event)?;
         }
 
-        println!("{:#?}", company);
+        tx.commit()?;
 
-        Ok(company)
+        Ok(events.len())
     }
+}
 
-    fn save(&self, db: &Database) -> Result<(), Box<dyn Error>> {
-        let events = self.get_uncommited_events();
+impl Company {
+    /// Identifier used to scope this aggregate's stream inside the shared
+    /// `events` table.
+    const AGGREGATE: &'static str = "company";
 
-        println!("{:#?}", events);
+    /// Rebuild a `Company` purely from its recorded event stream, folding
+    /// every event through the same state transition `apply` uses so the
+    /// in-memory aggregate is always derived from history.
+    pub fn from_events(events: impl IntoIterator<Item = CompanyEvents>) -> Self {
+        let mut company = Company::default();
 
-        for domain_event in events.iter() {
-            match &domain_event.event {
-                CompanyEvents::DepartmentAdded(department) => {
-                    db.execute(
-                        "INSERT INTO departments (id, name) values (?1, ?2)",
-                        &[&department.id, &department.name],
-                    )?;
-                }
-                CompanyEvents::EmployeeHired(employee) => {
-                    db.execute(
-                        "INSERT INTO employees (id, name, department_id) values (?1, ?2, ?3)",
-                        &[&employee.id, &employee.name, &employee.department_id],
-                    )?;
-                }
-            }
+        for event in events {
+            company.mutate(&event);
+        }
+
+        company
+    }
+
+    /// Seed the event store from the legacy `departments`/`employees`
+    /// projection rows so a database populated under the pre-event-sourcing
+    /// schema is not loaded as an empty `Company`. Idempotent: does nothing
+    /// once the stream already holds events.
+    pub fn backfill(db: &Database) -> Result<(), Box<dyn Error>> {
+        let recorded: i64 = db.query_row(
+            "SELECT COUNT(*) FROM events WHERE aggregate = ?1",
+            [Company::AGGREGATE],
+            |row| row.get(0),
+        )?;
+
+        if recorded > 0 {
+            return Ok(());
         }
 
+        let mut departments_stmt = db.prepare("SELECT id, name FROM departments")?;
+        let departments: Vec<Department> = departments_stmt
+            .query_map([], |row| {
+                Ok(Department {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut employees_stmt = db.prepare("SELECT id, name, department_id FROM employees")?;
+        let employees: Vec<Employee> = employees_stmt
+            .query_map([], |row| {
+                Ok(Employee {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    department_id: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        if departments.is_empty() && employees.is_empty() {
+            return Ok(());
+        }
+
+        let synthetic = departments
+            .into_iter()
+            .map(CompanyEvents::DepartmentAdded)
+            .chain(employees.into_iter().map(CompanyEvents::EmployeeHired));
+
+        let tx = db.unchecked_transaction()?;
+        let mut sequence: i64 = 0;
+
+        for event in synthetic {
+            sequence += 1;
+            tx.execute(
+                "INSERT INTO events (id, aggregate, sequence, type, payload_json)
+                    values (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    cuid::cuid2(),
+                    Company::AGGREGATE,
+                    sequence,
+                    event.event_type(),
+                    serde_json::to_string(&event)?
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+
         Ok(())
     }
-}
 
-impl Company {
     pub fn add_department(&mut self, department_name: String) -> Result<(), String> {
         let department_name = department_name.trim().to_lowercase();
 
-        if self.find_department(&department_name).is_some() {
+        if let Match::Exact(_) = self.find_department(&department_name) {
             return Err(format!(
                 "El departamento {} ya forma parte de la compañía",
                 department_name
             ));
         }
 
-        let department = Rc::new(Department {
+        let department = Department {
             id: cuid::cuid2(),
             name: department_name,
-        });
+        };
 
-        self.departments.push(Rc::clone(&department));
-        self.apply(CompanyEvents::DepartmentAdded(Rc::clone(&department)));
+        self.apply(CompanyEvents::DepartmentAdded(department));
 
         Ok(())
     }
@@ -135,23 +220,129 @@ impl Company {
         employee_name: String,
         department_name: String,
     ) -> Result<(), String> {
-        if let Some(department) = self.find_department(&department_name) {
-            let employee = Rc::new(Employee {
-                id: cuid::cuid2(),
-                name: employee_name.trim().to_string(),
-                department_id: department.id.clone(),
-            });
-
-            self.employees.push(Rc::clone(&employee));
-            self.apply(CompanyEvents::EmployeeHired(Rc::clone(&employee)));
-
-            Ok(())
-        } else {
-            Err(format!(
-                "No se ha encontrado el departamento {}",
+        let department_id = match self.find_department(&department_name) {
+            Match::Exact(department) => department.id.clone(),
+            Match::Suggestion(department) => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}. ¿Quiso decir '{}'?",
+                    department_name, department.name
+                ));
+            }
+            Match::None => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}",
+                    department_name
+                ));
+            }
+        };
+
+        let employee = Employee {
+            id: cuid::cuid2(),
+            name: employee_name.trim().to_string(),
+            department_id,
+        };
+
+        self.apply(CompanyEvents::EmployeeHired(employee));
+
+        Ok(())
+    }
+
+    pub fn fire_employee(&mut self, employee_name: String) -> Result<(), String> {
+        let employee_name = employee_name.trim().to_string();
+
+        let id = match self.find_employee(&employee_name) {
+            Some(employee) => employee.id.clone(),
+            None => return Err(self.employee_not_found(&employee_name)),
+        };
+
+        self.apply(CompanyEvents::EmployeeFired { id });
+
+        Ok(())
+    }
+
+    pub fn remove_department(
+        &mut self,
+        department_name: String,
+        cascade: bool,
+    ) -> Result<(), String> {
+        let department_name = department_name.trim().to_lowercase();
+
+        let department = match self.find_department(&department_name) {
+            Match::Exact(department) => Rc::clone(department),
+            Match::Suggestion(department) => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}. ¿Quiso decir '{}'?",
+                    department_name, department.name
+                ));
+            }
+            Match::None => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}",
+                    department_name
+                ));
+            }
+        };
+
+        let members: Vec<String> = self
+            .employees
+            .iter()
+            .filter(|employee| employee.department_id == department.id)
+            .map(|employee| employee.id.clone())
+            .collect();
+
+        if !members.is_empty() && !cascade {
+            return Err(format!(
+                "El departamento {} aún tiene empleados asignados",
                 department_name
-            ))
+            ));
+        }
+
+        for id in members {
+            self.apply(CompanyEvents::EmployeeFired { id });
         }
+
+        self.apply(CompanyEvents::DepartmentRemoved {
+            id: department.id.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn transfer_employee(
+        &mut self,
+        employee_name: String,
+        new_department_name: String,
+    ) -> Result<(), String> {
+        let employee_name = employee_name.trim().to_string();
+        let new_department_name = new_department_name.trim().to_lowercase();
+
+        let new_department_id = match self.find_department(&new_department_name) {
+            Match::Exact(department) => department.id.clone(),
+            Match::Suggestion(department) => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}. ¿Quiso decir '{}'?",
+                    new_department_name, department.name
+                ));
+            }
+            Match::None => {
+                return Err(format!(
+                    "No se ha encontrado el departamento {}",
+                    new_department_name
+                ));
+            }
+        };
+
+        let id = match self.find_employee(&employee_name) {
+            Some(employee) => employee.id.clone(),
+            None => return Err(self.employee_not_found(&employee_name)),
+        };
+
+        self.apply(CompanyEvents::EmployeeTransferred {
+            id,
+            new_department_id,
+        });
+
+        Ok(())
     }
 
     pub fn get_total_employees(&self) -> u32 {
@@ -167,9 +358,209 @@ impl Company {
             .len() as u32
     }
 
-    fn find_department(&self, department_name: &String) -> Option<&Rc<Department>> {
-        self.departments
+    fn find_department(&self, department_name: &String) -> Match<'_> {
+        let query = department_name.trim().to_lowercase();
+
+        if let Some(department) = self.departments.iter().find(|d| d.name == query) {
+            return Match::Exact(department);
+        }
+
+        let threshold = 1.max(query.chars().count() / 4);
+
+        let closest = self
+            .departments
+            .iter()
+            .map(|department| (levenshtein(&query, &department.name), department))
+            .filter(|(distance, _)| *distance <= threshold)
+            .min_by_key(|(distance, _)| *distance);
+
+        match closest {
+            Some((_, department)) => Match::Suggestion(department),
+            None => Match::None,
+        }
+    }
+
+    fn find_employee(&self, employee_name: &String) -> Option<&Rc<Employee>> {
+        self.employees
+            .iter()
+            .find(|employee| employee.name == *employee_name)
+    }
+
+    /// Build a "not found" message, offering the closest fuzzy match as a
+    /// "¿Quiso decir ...?" correction when there is one.
+    fn employee_not_found(&self, employee_name: &str) -> String {
+        match self.search_employees(employee_name).first() {
+            Some(employee) => format!(
+                "No se ha encontrado el empleado {}. ¿Quiso decir '{}'?",
+                employee_name, employee.name
+            ),
+            None => format!("No se ha encontrado el empleado {}", employee_name),
+        }
+    }
+
+    /// Fuzzy-search employees by name, returning every candidate within the
+    /// edit-distance threshold ordered from closest to furthest so the CLI
+    /// can offer corrections for a mistyped query.
+    pub fn search_employees(&self, query: &str) -> Vec<&Rc<Employee>> {
+        let query = query.trim().to_lowercase();
+        let threshold = 1.max(query.chars().count() / 4);
+
+        let mut matches: Vec<(usize, &Rc<Employee>)> = self
+            .employees
+            .iter()
+            .map(|employee| (levenshtein(&query, &employee.name.to_lowercase()), employee))
+            .filter(|(distance, _)| *distance <= threshold)
+            .collect();
+
+        matches.sort_by_key(|(distance, _)| *distance);
+        matches.into_iter().map(|(_, employee)| employee).collect()
+    }
+
+    /// Apply an event's state transition to the in-memory projection without
+    /// recording it as an uncommitted event — shared by `apply` and replay.
+    fn mutate(&mut self, event: &CompanyEvents) {
+        match event {
+            CompanyEvents::DepartmentAdded(department) => {
+                self.departments.push(Rc::new(department.clone()))
+            }
+            CompanyEvents::EmployeeHired(employee) => {
+                self.employees.push(Rc::new(employee.clone()))
+            }
+            CompanyEvents::EmployeeFired { id } => {
+                self.employees.retain(|employee| employee.id != *id)
+            }
+            CompanyEvents::DepartmentRemoved { id } => {
+                self.departments.retain(|department| department.id != *id)
+            }
+            CompanyEvents::EmployeeTransferred {
+                id,
+                new_department_id,
+            } => {
+                if let Some(slot) = self.employees.iter_mut().find(|employee| employee.id == *id) {
+                    *slot = Rc::new(Employee {
+                        id: id.clone(),
+                        name: slot.name.clone(),
+                        department_id: new_department_id.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Keep the `departments`/`employees` read-model tables in sync after an
+    /// event has been appended to the store.
+    fn project(&self, db: &Database, event: &CompanyEvents) -> Result<(), Box<dyn Error>> {
+        match event {
+            CompanyEvents::DepartmentAdded(department) => {
+                db.execute(
+                    "INSERT INTO departments (id, name) values (?1, ?2)",
+                    &[&department.id, &department.name],
+                )?;
+            }
+            CompanyEvents::EmployeeHired(employee) => {
+                db.execute(
+                    "INSERT INTO employees (id, name, department_id) values (?1, ?2, ?3)",
+                    &[&employee.id, &employee.name, &employee.department_id],
+                )?;
+            }
+            CompanyEvents::EmployeeFired { id } => {
+                db.execute("DELETE FROM employees WHERE id = ?1", &[id])?;
+            }
+            CompanyEvents::DepartmentRemoved { id } => {
+                db.execute("DELETE FROM departments WHERE id = ?1", &[id])?;
+            }
+            CompanyEvents::EmployeeTransferred {
+                id,
+                new_department_id,
+            } => {
+                db.execute(
+                    "UPDATE employees SET department_id = ?1 WHERE id = ?2",
+                    &[new_department_id, id],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Classic dynamic-programming Levenshtein edit distance between two
+/// strings, compared over their `char` vectors.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (m, n) = (a.len(), b.len());
+
+    let mut d = vec![vec![0usize; n + 1]; m + 1];
+    for i in 0..=m {
+        d[i][0] = i;
+    }
+    for j in 0..=n {
+        d[0][j] = j;
+    }
+
+    for i in 1..=m {
+        for j in 1..=n {
+            let substitution = if a[i - 1] != b[j - 1] { 1 } else { 0 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + substitution);
+        }
+    }
+
+    d[m][n]
+}
+
+impl CompanyEvents {
+    /// Stable discriminant persisted in the `type` column of the event store.
+    fn event_type(&self) -> &'static str {
+        match self {
+            CompanyEvents::DepartmentAdded(_) => "DepartmentAdded",
+            CompanyEvents::EmployeeHired(_) => "EmployeeHired",
+            CompanyEvents::EmployeeFired { .. } => "EmployeeFired",
+            CompanyEvents::DepartmentRemoved { .. } => "DepartmentRemoved",
+            CompanyEvents::EmployeeTransferred { .. } => "EmployeeTransferred",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaying_the_event_stream_reproduces_the_company() {
+        let mut company = Company::default();
+        company.add_department("Engineering".to_string()).unwrap();
+        company
+            .hire_employee("Sally".to_string(), "engineering".to_string())
+            .unwrap();
+
+        let stream: Vec<CompanyEvents> = company
+            .get_uncommited_events()
             .iter()
-            .find(|department| department.name == *department_name)
+            .map(|domain_event| domain_event.event.clone())
+            .collect();
+
+        let replayed = Company::from_events(stream);
+
+        assert_eq!(replayed.departments, company.departments);
+        assert_eq!(replayed.employees, company.employees);
+    }
+
+    #[test]
+    fn find_department_suggests_the_closest_match_for_a_typo() {
+        let mut company = Company::default();
+        company.add_department("Engineering".to_string()).unwrap();
+
+        match company.find_department(&"enginering".to_string()) {
+            Match::Suggestion(department) => assert_eq!(department.name, "engineering"),
+            _ => panic!("expected a suggestion for a close typo"),
+        }
+
+        assert!(matches!(
+            company.find_department(&"marketing".to_string()),
+            Match::None
+        ));
     }
 }
@@ -10,7 +10,7 @@ pub trait AggregateRoot<Event> {
 
 pub trait Repository<E, A: AggregateRoot<E>> {
     fn get(db: &Database) -> Result<A, Box<dyn Error>>;
-    fn save(&self, db: &Database) -> Result<(), Box<dyn Error>>;
+    fn save(&self, db: &Database) -> Result<usize, Box<dyn Error>>;
 }
 
 #[derive(Debug)]
@@ -12,9 +12,12 @@ use std::{
     rc::Rc,
 };
 
+pub mod command;
 pub mod company;
 pub mod domain;
-use crate::company::Employee;
+pub mod migrations;
+use crate::command::{Command, ListTarget};
+use crate::company::{Department, Employee};
 pub use company::Company;
 
 #[derive(PartialEq)]
@@ -23,6 +26,7 @@ enum Possibilities {
     HireEmployee,
     ViewList,
     GenerateReport,
+    CommandMode,
     Quit,
 }
 
@@ -33,6 +37,7 @@ fn next_choice() -> Option<Possibilities> {
         .item("Contratar empleado")
         .item("Ver lista")
         .item("Generar reporte")
+        .item("Modo comando")
         .item("Salir")
         .interact()
         .unwrap();
@@ -42,7 +47,8 @@ fn next_choice() -> Option<Possibilities> {
         1 => Some(Possibilities::HireEmployee),
         2 => Some(Possibilities::ViewList),
         3 => Some(Possibilities::GenerateReport),
-        4 => Some(Possibilities::Quit),
+        4 => Some(Possibilities::CommandMode),
+        5 => Some(Possibilities::Quit),
         _ => None,
     }
 }
@@ -58,22 +64,8 @@ pub fn run() -> Result<(), Box<dyn Error>> {
 
     let db = rusqlite::Connection::open(path)?;
 
-    db.execute(
-        "CREATE TABLE IF NOT EXISTS departments (
-        id TEXT PRIMARY KEY,
-        name TEXT NOT NULL UNIQUE
-    )",
-        [],
-    )?;
-
-    db.execute(
-        "CREATE TABLE IF NOT EXISTS employees (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL UNIQUE,
-            department_id TEXT NOT NULL REFERENCES departments(id)
-    )",
-        [],
-    )?;
+    migrations::migrate(&db)?;
+    Company::backfill(&db)?;
 
     let mut company = Company::get(&db)?;
     let mut next = next_choice().unwrap();
@@ -84,6 +76,7 @@ pub fn run() -> Result<(), Box<dyn Error>> {
             Possibilities::HireEmployee => hire_employee(&db, &mut company)?,
             Possibilities::ViewList => view_list(&company)?,
             Possibilities::GenerateReport => generate_report(&company)?,
+            Possibilities::CommandMode => command_mode(&db, &mut company)?,
             _ => (),
         }
 
@@ -98,8 +91,7 @@ fn add_department(db: &Database, company: &mut Company) -> Result<(), Box<dyn Er
 
     if let Err(err) = company.add_department(department) {
         eprintln!("{err}");
-    } else {
-        company.save(db)?;
+    } else if company.save(db)? > 0 {
         company.commit();
     };
 
@@ -123,14 +115,123 @@ fn hire_employee(db: &Database, company: &mut Company) -> Result<(), Box<dyn Err
 
     if let Err(err) = company.hire_employee(employee, department) {
         eprintln!("{err}");
-    } else {
-        company.save(db)?;
+    } else if company.save(db)? > 0 {
         company.commit();
     };
 
     Ok(())
 }
 
+fn command_mode(db: &Database, company: &mut Company) -> Result<(), Box<dyn Error>> {
+    println!("Escriba comandos (add/move/list/remove/quit). 'quit' para volver.");
+
+    let input = stdin();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let sentence = line.trim();
+        if sentence.is_empty() {
+            continue;
+        }
+
+        match command::parse(sentence) {
+            Ok(Command::Quit) => break,
+            Ok(cmd) => dispatch(db, company, cmd)?,
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn dispatch(db: &Database, company: &mut Company, command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Add {
+            employee,
+            department,
+        } => {
+            let result = company.hire_employee(employee, department);
+            persist(db, company, result)
+        }
+        Command::Transfer {
+            employee,
+            department,
+        } => {
+            let result = company.transfer_employee(employee, department);
+            persist(db, company, result)
+        }
+        Command::Remove { department } => {
+            let result = company.remove_department(department, false);
+            persist(db, company, result)
+        }
+        Command::List(ListTarget::All) => view_list(company),
+        Command::List(ListTarget::Department(name)) => view_department(company, &name),
+        Command::Quit => Ok(()),
+    }
+}
+
+fn persist(
+    db: &Database,
+    company: &mut Company,
+    result: Result<(), String>,
+) -> Result<(), Box<dyn Error>> {
+    match result {
+        Err(err) => eprintln!("{err}"),
+        Ok(()) => {
+            if company.save(db)? > 0 {
+                company.commit();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn view_department(company: &Company, department_name: &str) -> Result<(), Box<dyn Error>> {
+    match company
+        .departments
+        .iter()
+        .find(|department| department.name == department_name)
+    {
+        Some(department) => print_department(company, department),
+        None => eprintln!("No se ha encontrado el departamento {}", department_name),
+    }
+
+    Ok(())
+}
+
+fn print_department(company: &Company, department: &Department) {
+    println!(
+        "\n{}",
+        format!("Departamento {}", department.name)
+            .bold()
+            .underline()
+    );
+
+    let employees_in_department = company
+        .employees
+        .iter()
+        .cloned()
+        .filter(|employee| employee.department_id == department.id)
+        .collect::<Vec<Rc<Employee>>>();
+
+    if employees_in_department.is_empty() {
+        println!("Sin empleados");
+    }
+
+    for (i, employee) in employees_in_department.iter().enumerate() {
+        println!("{}. {}", i + 1, employee.name);
+    }
+
+    println!();
+}
+
 fn ask_for_stdin(label: &str) -> Result<String, Box<dyn Error>> {
     let mut input = String::new();
     println!("{label}");
@@ -152,29 +253,7 @@ fn ask_for_department(departments: &[String]) -> Result<String, Box<dyn Error>>
 
 fn view_list(company: &Company) -> Result<(), Box<dyn Error>> {
     for department in company.departments.iter() {
-        println!(
-            "\n{}",
-            format!("Departamento {}", department.name)
-                .bold()
-                .underline()
-        );
-
-        let employees_in_department = company
-            .employees
-            .iter()
-            .cloned()
-            .filter(|employee| employee.department_id == department.id)
-            .collect::<Vec<Rc<Employee>>>();
-
-        if employees_in_department.len() == 0 {
-            println!("Sin empleados");
-        }
-
-        for (i, employee) in employees_in_department.iter().enumerate() {
-            println!("{}. {}", i + 1, employee.name);
-        }
-
-        println!();
+        print_department(company, department);
     }
 
     Ok(())
@@ -0,0 +1,75 @@
+//! Versioned schema migrations. On startup [`migrate`] applies every
+//! migration whose version is higher than the one recorded in the
+//! `schema_version` table, each inside its own transaction, so the database
+//! can evolve safely and restarting never re-runs an applied migration.
+
+use std::error::Error;
+
+use crate::Database;
+
+/// A single forward schema change, identified by a monotonically increasing
+/// `version` and the SQL that brings the database up to it.
+struct Migration {
+    version: i64,
+    up_sql: &'static str,
+}
+
+/// The ordered list of migrations. Append new entries with the next version
+/// number — never edit or reorder an already-released migration.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        up_sql: "CREATE TABLE IF NOT EXISTS departments (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE
+        )",
+    },
+    Migration {
+        version: 2,
+        up_sql: "CREATE TABLE IF NOT EXISTS employees (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            department_id TEXT NOT NULL REFERENCES departments(id)
+        )",
+    },
+    Migration {
+        version: 3,
+        up_sql: "CREATE TABLE IF NOT EXISTS events (
+            id TEXT PRIMARY KEY,
+            aggregate TEXT NOT NULL,
+            sequence INTEGER NOT NULL,
+            type TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            occurred_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE (aggregate, sequence)
+        )",
+    },
+];
+
+/// Apply every pending migration, recording its version in the same
+/// transaction as its SQL so both commit together. Idempotent:
+/// already-applied migrations are skipped.
+pub fn migrate(db: &Database) -> Result<(), Box<dyn Error>> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)",
+        [],
+    )?;
+
+    let current: i64 = db.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = db.unchecked_transaction()?;
+        tx.execute_batch(migration.up_sql)?;
+        tx.execute(
+            "INSERT INTO schema_version (version) values (?1)",
+            [migration.version],
+        )?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}